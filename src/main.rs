@@ -2,10 +2,18 @@ mod server;
 
 use memo_stt::SttEngine;
 use server::create_router;
-use server::state::ServerState;
+use server::state::{AudioTransport, ServerState};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 
+/// Env var holding a hex-encoded 32-byte pre-shared key. When set, `/audio`
+/// requests announcing `X-Audio-Encryption: chacha20` are decrypted with it.
+const AUDIO_ENCRYPTION_KEY_ENV: &str = "AUDIO_ENCRYPTION_KEY";
+
+/// CLI flag that opens the host machine's default microphone as an
+/// additional audio source, registered under the `"local-mic"` device id.
+const LOCAL_MIC_FLAG: &str = "--local-mic";
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     println!("🚀 Starting memo-esp-server...");
@@ -18,12 +26,28 @@ async fn main() -> anyhow::Result<()> {
     engine.warmup()?;
     println!("✓ Model ready!");
     
+    // Configure the audio transport: plaintext unless a pre-shared key is provided
+    let transport = match std::env::var(AUDIO_ENCRYPTION_KEY_ENV) {
+        Ok(hex_key) => {
+            let key = hex::decode(hex_key.trim())
+                .expect("AUDIO_ENCRYPTION_KEY must be valid hex");
+            println!("🔒 Audio encryption enabled (chacha20)");
+            AudioTransport::Encrypted { key }
+        }
+        Err(_) => AudioTransport::Plain,
+    };
+
     // Create server state
-    let state = Arc::new(ServerState::new(engine));
-    
+    let state = Arc::new(ServerState::with_transport(engine, transport));
+
+    // Optionally capture audio from the host machine's own microphone
+    if std::env::args().any(|arg| arg == LOCAL_MIC_FLAG) {
+        server::capture::start(state.clone())?;
+    }
+
     // Create router
-    let app = create_router(state);
-    
+    let app = create_router(state.clone());
+
     // Create directories if they don't exist
     std::fs::create_dir_all("received_audio")?;
     std::fs::create_dir_all("transcripts")?;
@@ -34,6 +58,8 @@ async fn main() -> anyhow::Result<()> {
     println!("📡 Server listening on http://0.0.0.0:8000");
     println!("   UI: http://localhost:8000/");
     println!("   Audio endpoint: http://localhost:8000/audio");
+    println!("   Streaming audio endpoint: http://localhost:8000/audio-stream");
+    println!("   (pass --local-mic to also transcribe this machine's microphone)");
     println!("   Status endpoint: http://localhost:8000/status");
     println!("\n⌨️  Press Ctrl+C to stop\n");
     
@@ -1,7 +1,8 @@
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use opus::{Channels, Decoder};
 
 #[derive(Debug, Clone)]
 pub struct AudioQuality {
@@ -9,6 +10,63 @@ pub struct AudioQuality {
     pub duration_sec: f32,
 }
 
+/// Decode a body made of length-delimited Opus frames (each a 2-byte
+/// big-endian length prefix followed by the payload) into i16 PCM at the
+/// negotiated rate/channels. Frames are decoded in order by a single decoder
+/// and their samples concatenated, so the rest of the pipeline (quality analysis,
+/// WAV save, transcription) is unchanged from the raw-PCM path.
+pub fn decode_opus_frames(body: &[u8], sample_rate: u32, channels: u16) -> Result<Vec<i16>> {
+    let opus_channels = match channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        _ => return Err(anyhow!("opus decoding only supports 1 or 2 channels")),
+    };
+    let mut decoder = Decoder::new(sample_rate, opus_channels)?;
+
+    let mut samples = Vec::new();
+    let mut out = vec![0i16; 5760 * channels as usize]; // max Opus frame: 120ms @ 48kHz
+    let mut offset = 0;
+
+    while offset + 2 <= body.len() {
+        let frame_len = u16::from_be_bytes([body[offset], body[offset + 1]]) as usize;
+        offset += 2;
+
+        if offset + frame_len > body.len() {
+            return Err(anyhow!("truncated opus frame"));
+        }
+
+        let payload = &body[offset..offset + frame_len];
+        offset += frame_len;
+
+        let decoded_samples = decoder.decode(payload, &mut out, false)?;
+        samples.extend_from_slice(&out[..decoded_samples * channels as usize]);
+    }
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_body_decodes_to_no_samples() {
+        assert_eq!(decode_opus_frames(&[], 16000, 1).unwrap(), Vec::<i16>::new());
+    }
+
+    #[test]
+    fn rejects_unsupported_channel_count() {
+        assert!(decode_opus_frames(&[], 16000, 3).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_frame() {
+        // Announces a 10-byte payload but only provides 2 bytes after the
+        // length prefix.
+        let body = [0u8, 10, 0xAA, 0xBB];
+        assert!(decode_opus_frames(&body, 16000, 1).is_err());
+    }
+}
 
 /// Save PCM data as WAV file
 pub fn save_wav_file(
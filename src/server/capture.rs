@@ -0,0 +1,155 @@
+//! Optional local microphone capture, so the transcription pipeline can be
+//! exercised from the machine hosting the server without an ESP32.
+//!
+//! Samples are pushed through [`crate::server::handlers::ingest_stream_frame`]
+//! under a synthetic `"local-mic"` device id, so the VAD/SSE/archival
+//! pipeline runs identically whether audio arrived over the network or from
+//! this device.
+
+use crate::server::handlers::ingest_stream_frame;
+use crate::server::state::{DeviceInfo, ServerState};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub const LOCAL_MIC_DEVICE_ID: &str = "local-mic";
+
+/// 20ms frames at the engine's 16kHz input rate, matching the `/audio-stream` cadence.
+const ENGINE_SAMPLE_RATE: u32 = 16000;
+const FRAME_MS: u32 = 20;
+const FRAME_SAMPLES: usize = (ENGINE_SAMPLE_RATE * FRAME_MS / 1000) as usize;
+
+/// Open the default input device and start streaming captured audio into
+/// the same VAD/transcription pipeline used for network audio. Spawns its
+/// own background tasks and returns as soon as they are spawned; the
+/// `cpal::Stream` is built, started and then kept alive on a dedicated
+/// thread for the life of the process. Errors building or starting the
+/// stream on that thread are logged, not returned from this function.
+pub fn start(state: Arc<ServerState>) -> Result<()> {
+    let host = cpal::default_host();
+    let device = host
+        .default_input_device()
+        .ok_or_else(|| anyhow!("no default input device found"))?;
+    let config = device.default_input_config()?;
+
+    println!("🎙️  Local microphone capture enabled: {}", device.name()?);
+
+    state.devices.lock().unwrap().insert(
+        LOCAL_MIC_DEVICE_ID.to_string(),
+        DeviceInfo {
+            device_id: LOCAL_MIC_DEVICE_ID.to_string(),
+            last_seen: Utc::now(),
+            ip_address: None,
+        },
+    );
+
+    let source_rate = config.sample_rate().0;
+    let channels = config.channels() as usize;
+    let sample_format = config.sample_format();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<i16>>();
+
+    tokio::spawn(async move {
+        let mut pending = Vec::new();
+        while let Some(mut samples) = rx.recv().await {
+            // Refresh last_seen on every batch so `/devices` (which drops
+            // anything idle for >10s) doesn't age out a mic that's still
+            // capturing.
+            if let Some(info) = state.devices.lock().unwrap().get_mut(LOCAL_MIC_DEVICE_ID) {
+                info.last_seen = Utc::now();
+            }
+
+            pending.append(&mut samples);
+            while pending.len() >= FRAME_SAMPLES {
+                let frame: Vec<i16> = pending.drain(..FRAME_SAMPLES).collect();
+                let _ = ingest_stream_frame(
+                    state.clone(),
+                    LOCAL_MIC_DEVICE_ID.to_string(),
+                    ENGINE_SAMPLE_RATE,
+                    frame,
+                )
+                .await;
+            }
+        }
+    });
+
+    // cpal streams aren't `Send`, so they're built and driven on a
+    // dedicated OS thread rather than handed to the tokio runtime.
+    std::thread::spawn(move || {
+        let err_fn = |err| eprintln!("❌ Microphone capture error: {}", err);
+
+        let stream = match sample_format {
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _| {
+                    let mono = downmix_to_mono(data, channels);
+                    let resampled = resample_to_16k(&mono, source_rate);
+                    let _ = tx.send(resampled);
+                },
+                err_fn,
+                None,
+            ),
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _| {
+                    let pcm: Vec<i16> = data
+                        .iter()
+                        .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+                        .collect();
+                    let mono = downmix_to_mono(&pcm, channels);
+                    let resampled = resample_to_16k(&mono, source_rate);
+                    let _ = tx.send(resampled);
+                },
+                err_fn,
+                None,
+            ),
+            other => {
+                eprintln!("❌ Unsupported microphone sample format: {:?}", other);
+                return;
+            }
+        };
+
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = stream.play() {
+                    eprintln!("❌ Failed to start microphone stream: {}", e);
+                    return;
+                }
+                // Park this thread for the life of the process; dropping
+                // `stream` would stop capture.
+                loop {
+                    std::thread::sleep(std::time::Duration::from_secs(60));
+                }
+            }
+            Err(e) => eprintln!("❌ Failed to build microphone stream: {}", e),
+        }
+    });
+
+    Ok(())
+}
+
+fn downmix_to_mono(data: &[i16], channels: usize) -> Vec<i16> {
+    if channels <= 1 {
+        return data.to_vec();
+    }
+
+    data.chunks_exact(channels)
+        .map(|frame| (frame.iter().map(|&s| s as i32).sum::<i32>() / channels as i32) as i16)
+        .collect()
+}
+
+/// Nearest-neighbor resample to the engine's 16kHz input rate. Good enough
+/// for local testing; the ESP32 path already sends 16kHz PCM directly.
+fn resample_to_16k(samples: &[i16], source_rate: u32) -> Vec<i16> {
+    if source_rate == ENGINE_SAMPLE_RATE || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = source_rate as f64 / ENGINE_SAMPLE_RATE as f64;
+    let out_len = (samples.len() as f64 / ratio) as usize;
+    (0..out_len)
+        .map(|i| samples[((i as f64 * ratio) as usize).min(samples.len() - 1)])
+        .collect()
+}
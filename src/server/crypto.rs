@@ -0,0 +1,58 @@
+//! Keystream ciphers for [`crate::server::state::AudioTransport::Encrypted`].
+
+use anyhow::{anyhow, Result};
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+
+/// Nonce length required by the `chacha20` scheme.
+pub const NONCE_LEN: usize = 12;
+
+/// XOR `data` in place with a ChaCha20 keystream derived from `key`/`nonce`.
+/// XOR is its own inverse, so this same function both encrypts and decrypts.
+pub fn chacha20_xor(key: &[u8], nonce: &[u8], data: &mut [u8]) -> Result<()> {
+    let key: [u8; 32] = key
+        .try_into()
+        .map_err(|_| anyhow!("chacha20 key must be 32 bytes"))?;
+    let nonce: [u8; NONCE_LEN] = nonce
+        .try_into()
+        .map_err(|_| anyhow!("chacha20 nonce must be {} bytes", NONCE_LEN))?;
+
+    let mut cipher = ChaCha20::new(&key.into(), &nonce.into());
+    cipher.apply_keystream(data);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_then_decrypt_round_trips() {
+        let key = [7u8; 32];
+        let nonce = [1u8; NONCE_LEN];
+        let original = b"some audio bytes".to_vec();
+
+        let mut data = original.clone();
+        chacha20_xor(&key, &nonce, &mut data).unwrap();
+        assert_ne!(data, original);
+
+        chacha20_xor(&key, &nonce, &mut data).unwrap();
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn rejects_wrong_key_length() {
+        let key = [7u8; 16];
+        let nonce = [1u8; NONCE_LEN];
+        let mut data = b"hello".to_vec();
+        assert!(chacha20_xor(&key, &nonce, &mut data).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_nonce_length() {
+        let key = [7u8; 32];
+        let nonce = [1u8; 4];
+        let mut data = b"hello".to_vec();
+        assert!(chacha20_xor(&key, &nonce, &mut data).is_err());
+    }
+}
@@ -1,9 +1,14 @@
-use crate::server::audio::{analyze_audio_quality, save_wav_file};
-use crate::server::state::{ServerState, Transcript};
+use crate::server::audio::{analyze_audio_quality, decode_opus_frames, save_wav_file};
+use crate::server::crypto::chacha20_xor;
+use crate::server::state::{
+    AudioTransport, ServerState, StreamSegment, Transcript, TranscriptSegment,
+    PARTIAL_DEBOUNCE_MS, SEGMENT_OVERLAP_MS, SEGMENT_SILENCE_CLOSE_MS,
+};
+use crate::server::vad::{frame_rms, is_voiced, noise_floor};
 use axum::{
-    body::Bytes,
+    body::{Body, Bytes},
     extract::{Query, State, ConnectInfo},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, Response, StatusCode},
     response::{IntoResponse, Sse},
     Json,
 };
@@ -11,7 +16,8 @@ use std::net::SocketAddr;
 use chrono::Utc;
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{Read, Seek};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -23,6 +29,50 @@ pub struct AudioQuery {
     rate: u32,
     bits: u16,
     channels: u16,
+    #[serde(default = "default_codec")]
+    codec: String,
+}
+
+fn default_codec() -> String {
+    "pcm".to_string()
+}
+
+/// Write a transcript to `transcripts/<device>_<timestamp>.json` (and a
+/// companion `.txt`), and return the JSON value that was written so callers
+/// can broadcast the same payload over SSE. Shared by the whole-upload
+/// `/audio` path and the streaming `/audio-stream` segment-close path so
+/// both sources of a transcript end up persisted and broadcast the same way.
+fn persist_transcript(transcript: &Transcript, duration_sec: f64) -> serde_json::Value {
+    let transcript_dir = PathBuf::from("transcripts");
+    fs::create_dir_all(&transcript_dir).ok();
+    let transcript_filename = format!(
+        "{}_{}.json",
+        transcript.device_id,
+        Utc::now().format("%Y%m%d_%H%M%S")
+    );
+    let transcript_path = transcript_dir.join(&transcript_filename);
+
+    let transcript_json = serde_json::json!({
+        "device_id": transcript.device_id,
+        "timestamp": transcript.timestamp.to_rfc3339(),
+        "transcript": transcript.text,  // UI expects "transcript" field
+        "text": transcript.text,  // Keep both for compatibility
+        "segments": transcript.segments,
+        "audio_file": transcript.audio_file,
+        "duration": duration_sec,  // UI expects duration field
+        "audio_quality": transcript.audio_quality,
+        "server_analysis": transcript.server_analysis,
+    });
+
+    if let Ok(json_str) = serde_json::to_string_pretty(&transcript_json) {
+        fs::write(&transcript_path, json_str).ok();
+        println!("📝 Transcript saved: {}", transcript_path.display());
+    }
+
+    let txt_path = transcript_dir.join(transcript_filename.replace(".json", ".txt"));
+    fs::write(&txt_path, &transcript.text).ok();
+
+    transcript_json
 }
 
 
@@ -53,13 +103,41 @@ pub async fn handle_audio(
         );
     }
 
+    // Decrypt the body if the server requires encryption. A server
+    // configured with a pre-shared key must never fall back to treating an
+    // unannounced body as plaintext - that would let a plaintext upload
+    // (rolled-back firmware, an eavesdropper, a bug) sail through silently.
+    let scheme = headers
+        .get("x-audio-encryption")
+        .and_then(|v| v.to_str().ok());
+
+    let mut audio_bytes = body.to_vec();
+    match (&state.transport, scheme) {
+        (AudioTransport::Encrypted { key }, Some("chacha20")) => {
+            let nonce_hex = headers
+                .get("x-audio-nonce")
+                .and_then(|v| v.to_str().ok())
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            let nonce = hex::decode(nonce_hex).map_err(|_| StatusCode::BAD_REQUEST)?;
+            chacha20_xor(key, &nonce, &mut audio_bytes).map_err(|_| StatusCode::BAD_REQUEST)?;
+        }
+        (AudioTransport::Encrypted { .. }, _) => {
+            eprintln!("⚠️  Rejected unencrypted /audio body from {} (server requires encryption)", device_id);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        (AudioTransport::Plain, Some(_)) => return Err(StatusCode::BAD_REQUEST),
+        (AudioTransport::Plain, None) => {}
+    }
+
     // Convert bytes to i16 samples
-    let pcm_samples: Vec<i16> = if bits_per_sample == 16 {
-        body.chunks_exact(2)
+    let pcm_samples: Vec<i16> = match params.codec.as_str() {
+        "opus" => decode_opus_frames(&audio_bytes, sample_rate, channels)
+            .map_err(|_| StatusCode::BAD_REQUEST)?,
+        "pcm" if bits_per_sample == 16 => audio_bytes
+            .chunks_exact(2)
             .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
-            .collect()
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+            .collect(),
+        _ => return Err(StatusCode::BAD_REQUEST),
     };
 
     if pcm_samples.is_empty() {
@@ -107,54 +185,47 @@ pub async fn handle_audio(
     let audio_quality_clone = audio_quality_json.clone();
     let server_analysis_clone = server_analysis.clone();
 
-    tokio::spawn(async move {
+    // Whisper inference is CPU-bound and can take far longer than a tokio
+    // worker thread should ever be blocked for, so it runs on the blocking
+    // thread pool rather than tying up the async runtime (which also serves
+    // /status, /devices, SSE, etc).
+    tokio::task::spawn_blocking(move || {
         println!("🔄 Transcribing...");
-        let mut engine = state_clone.engine.lock().unwrap();
-        
-        match engine.transcribe(&samples_clone) {
-            Ok(text) => {
+        let detailed = {
+            let mut engine = state_clone.engine.lock().unwrap();
+            engine.transcribe_detailed(&samples_clone)
+        };
+
+        match detailed {
+            Ok(result) => {
+                let text = result.text;
+                let segments: Vec<TranscriptSegment> = result
+                    .segments
+                    .into_iter()
+                    .map(|s| TranscriptSegment {
+                        start_sec: s.start_sec,
+                        end_sec: s.end_sec,
+                        text: s.text,
+                    })
+                    .collect();
+
                 // Calculate duration from server_analysis before moving it
                 let duration = server_analysis_clone.get("duration_sec")
                     .and_then(|v| v.as_f64())
                     .unwrap_or(0.0);
-                
+
                 let transcript = Transcript {
                     device_id: device_id_clone.clone(),
                     timestamp: Utc::now(),
                     text: text.clone(),
+                    segments: segments.clone(),
                     audio_file: Some(format!("received_audio/{}", wav_filename_clone)),
                     audio_quality: Some(audio_quality_clone.clone()),
                     server_analysis: Some(server_analysis_clone.clone()),
                 };
 
                 // Save transcript to disk
-                let transcript_dir = PathBuf::from("transcripts");
-                fs::create_dir_all(&transcript_dir).ok();
-                let transcript_filename = format!("{}_{}.json", 
-                    device_id_clone, 
-                    Utc::now().format("%Y%m%d_%H%M%S"));
-                let transcript_path = transcript_dir.join(&transcript_filename);
-                
-                let transcript_json = serde_json::json!({
-                    "device_id": transcript.device_id,
-                    "timestamp": transcript.timestamp.to_rfc3339(),
-                    "transcript": transcript.text,  // UI expects "transcript" field
-                    "text": transcript.text,  // Keep both for compatibility
-                    "audio_file": transcript.audio_file,
-                    "duration": duration,  // UI expects duration field
-                    "audio_quality": transcript.audio_quality,
-                    "server_analysis": transcript.server_analysis,
-                });
-
-                if let Ok(json_str) = serde_json::to_string_pretty(&transcript_json) {
-                    fs::write(&transcript_path, json_str).ok();
-                    println!("📝 Transcript saved: {}", transcript_path.display());
-                }
-
-                // Save text file
-                let txt_path = transcript_dir.join(
-                    transcript_filename.replace(".json", ".txt"));
-                fs::write(&txt_path, &text).ok();
+                let transcript_json = persist_transcript(&transcript, duration);
 
                 // Add to in-memory transcripts
                 {
@@ -180,10 +251,214 @@ pub async fn handle_audio(
     Ok(StatusCode::OK)
 }
 
-/// Handle GET /audio-file - serve WAV files
+/// Handle POST /audio-stream - receive one small PCM frame of a continuous
+/// stream, run voice-activity detection, and emit partial/final transcripts
+/// over SSE as speech segments open and close.
+pub async fn handle_audio_stream(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<AudioQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+    let device_id = params.device.clone();
+    let sample_rate = params.rate;
+
+    // The VAD timers (silence_ms/ms_since_partial) assume one sample per
+    // frame is one time step; an interleaved multi-channel frame would
+    // advance them ~`channels`x too fast, closing segments early and
+    // mistiming the partial debounce. Reject anything but mono for now.
+    if params.bits != 16 || params.channels != 1 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let frame: Vec<i16> = body
+        .chunks_exact(2)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+
+    if frame.is_empty() || sample_rate == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    ingest_stream_frame(state, device_id, sample_rate, frame).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Run one frame of a device's audio through the VAD segmenter, broadcasting
+/// partial/final transcripts as segments open and close. Shared by the
+/// `/audio-stream` HTTP handler and the local microphone capture subsystem
+/// so both sources drive the exact same pipeline.
+pub async fn ingest_stream_frame(
+    state: Arc<ServerState>,
+    device_id: String,
+    sample_rate: u32,
+    frame: Vec<i16>,
+) -> Result<(), StatusCode> {
+    let frame_duration_ms = frame.len() as f32 / sample_rate as f32 * 1000.0;
+    let rms = frame_rms(&frame);
+
+    let mut segments = state.stream_segments.lock().unwrap();
+    let segment = segments.entry(device_id.clone()).or_default();
+    segment.push_rms(rms);
+    let floor = noise_floor(segment.rms_history.make_contiguous());
+    let voiced = is_voiced(rms, floor);
+
+    if voiced {
+        segment.samples.extend_from_slice(&frame);
+        segment.silence_ms = 0.0;
+        segment.ms_since_partial += frame_duration_ms;
+        let was_open = segment.voiced;
+        segment.voiced = true;
+
+        if !was_open {
+            state.broadcast_sse(
+                "partial",
+                &serde_json::json!({ "device_id": device_id, "text": "" }),
+            );
+        }
+
+        // Debounce: only kick off a re-transcription every ~250ms of new
+        // voiced audio, and never while one is still running, so a long
+        // segment can't pile up tasks behind the single STT engine lock.
+        let should_transcribe =
+            !segment.partial_inflight && segment.ms_since_partial >= PARTIAL_DEBOUNCE_MS;
+        let partial_samples = if should_transcribe {
+            segment.ms_since_partial = 0.0;
+            segment.partial_inflight = true;
+            Some(segment.samples.clone())
+        } else {
+            None
+        };
+        drop(segments);
+
+        if let Some(partial_samples) = partial_samples {
+            let state_clone = state.clone();
+            let device_id_clone = device_id.clone();
+            tokio::task::spawn_blocking(move || {
+                let text = {
+                    let mut engine = state_clone.engine.lock().unwrap();
+                    engine.transcribe(&partial_samples)
+                };
+
+                if let Ok(text) = text {
+                    state_clone.broadcast_sse(
+                        "partial",
+                        &serde_json::json!({ "device_id": device_id_clone, "text": text }),
+                    );
+                }
+
+                if let Some(segment) = state_clone
+                    .stream_segments
+                    .lock()
+                    .unwrap()
+                    .get_mut(&device_id_clone)
+                {
+                    segment.partial_inflight = false;
+                }
+            });
+        }
+    } else if segment.voiced {
+        segment.samples.extend_from_slice(&frame);
+        segment.silence_ms += frame_duration_ms;
+
+        if segment.silence_ms >= SEGMENT_SILENCE_CLOSE_MS {
+            let closed_samples = std::mem::take(&mut segment.samples);
+            let overlap_samples = (SEGMENT_OVERLAP_MS / 1000.0 * sample_rate as f32) as usize;
+            let tail_start = closed_samples.len().saturating_sub(overlap_samples);
+            segment.samples.extend_from_slice(&closed_samples[tail_start..]);
+            segment.silence_ms = 0.0;
+            segment.voiced = false;
+            segment.ms_since_partial = 0.0;
+            drop(segments);
+
+            let state_clone = state.clone();
+            let device_id_clone = device_id.clone();
+            tokio::task::spawn_blocking(move || {
+                let detailed = {
+                    let mut engine = state_clone.engine.lock().unwrap();
+                    engine.transcribe_detailed(&closed_samples)
+                };
+                if let Ok(result) = detailed {
+                    let text = result.text;
+                    let segments: Vec<TranscriptSegment> = result
+                        .segments
+                        .into_iter()
+                        .map(|s| TranscriptSegment {
+                            start_sec: s.start_sec,
+                            end_sec: s.end_sec,
+                            text: s.text,
+                        })
+                        .collect();
+
+                    let transcript = Transcript {
+                        device_id: device_id_clone.clone(),
+                        timestamp: Utc::now(),
+                        text: text.clone(),
+                        segments,
+                        audio_file: None,
+                        audio_quality: None,
+                        server_analysis: None,
+                    };
+
+                    // Persist to transcripts/*.json like the /audio path,
+                    // so streamed transcripts also show up via GET /transcripts.
+                    let duration_sec = closed_samples.len() as f64 / sample_rate as f64;
+                    let transcript_json = persist_transcript(&transcript, duration_sec);
+
+                    {
+                        let mut transcripts = state_clone.transcripts.lock().unwrap();
+                        transcripts.push(transcript);
+                        if transcripts.len() > 1000 {
+                            transcripts.remove(0);
+                        }
+                    }
+
+                    // Broadcast the full payload (including segment timestamps),
+                    // not just device_id/text.
+                    state_clone.broadcast_sse("final", &transcript_json);
+                }
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a `Range: bytes=start-end` header into a clamped, inclusive
+/// `(start, end)` byte range, or `None` if it can't be satisfied.
+fn parse_byte_range(header_value: &str, total_len: usize) -> Option<(usize, usize)> {
+    if total_len == 0 {
+        return None;
+    }
+
+    let spec = header_value.strip_prefix("bytes=")?;
+    let (start_str, end_str) = spec.split_once('-')?;
+
+    let start: usize = if start_str.is_empty() {
+        0
+    } else {
+        start_str.parse().ok()?
+    };
+    let end: usize = if end_str.is_empty() {
+        total_len - 1
+    } else {
+        end_str.parse().ok()?
+    };
+    let end = end.min(total_len - 1);
+
+    if start > end {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+/// Handle GET /audio-file - serve WAV files, with `Range` support so the
+/// UI `<audio>` element can seek without re-downloading the whole file.
 pub async fn handle_audio_file(
     Query(params): Query<HashMap<String, String>>,
-) -> Result<impl IntoResponse, StatusCode> {
+    headers: HeaderMap,
+) -> Result<Response<Body>, StatusCode> {
     let filepath = params.get("path").ok_or(StatusCode::BAD_REQUEST)?;
     
     // Normalize path and prevent directory traversal
@@ -214,13 +489,50 @@ pub async fn handle_audio_file(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    let contents = fs::read(&full_path).map_err(|_| StatusCode::NOT_FOUND)?;
-    
-    Ok((
-        StatusCode::OK,
-        [("Content-Type", "audio/wav")],
-        contents,
-    ))
+    let mut file = File::open(&full_path).map_err(|_| StatusCode::NOT_FOUND)?;
+    let total_len = file
+        .metadata()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .len() as usize;
+
+    if let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        let Some((start, end)) = parse_byte_range(range, total_len) else {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total_len))
+                .body(Body::empty())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        };
+
+        // Read only the requested slice rather than the whole file.
+        file.seek(std::io::SeekFrom::Start(start as u64))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let mut slice = vec![0u8; end - start + 1];
+        file.read_exact(&mut slice)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return Response::builder()
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_TYPE, "audio/wav")
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, total_len),
+            )
+            .header(header::ACCEPT_RANGES, "bytes")
+            .body(Body::from(slice))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let mut contents = Vec::with_capacity(total_len);
+    file.read_to_end(&mut contents)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "audio/wav")
+        .header(header::ACCEPT_RANGES, "bytes")
+        .body(Body::from(contents))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 /// Handle GET /status - device status (used by ESP32 for polling)
@@ -445,3 +757,43 @@ pub async fn handle_events(
             .text("keep-alive-text"),
     )
 }
+
+#[cfg(test)]
+mod range_tests {
+    use super::parse_byte_range;
+
+    #[test]
+    fn full_range_open_ended() {
+        assert_eq!(parse_byte_range("bytes=0-", 100), Some((0, 99)));
+    }
+
+    #[test]
+    fn explicit_start_and_end() {
+        assert_eq!(parse_byte_range("bytes=10-20", 100), Some((10, 20)));
+    }
+
+    #[test]
+    fn end_is_clamped_to_file_length() {
+        assert_eq!(parse_byte_range("bytes=10-1000", 100), Some((10, 99)));
+    }
+
+    #[test]
+    fn missing_start_defaults_to_zero() {
+        assert_eq!(parse_byte_range("bytes=-50", 100), Some((0, 50)));
+    }
+
+    #[test]
+    fn start_past_end_is_unsatisfiable() {
+        assert_eq!(parse_byte_range("bytes=200-300", 100), None);
+    }
+
+    #[test]
+    fn empty_file_is_unsatisfiable() {
+        assert_eq!(parse_byte_range("bytes=0-10", 0), None);
+    }
+
+    #[test]
+    fn malformed_header_is_rejected() {
+        assert_eq!(parse_byte_range("not-a-range", 100), None);
+    }
+}
@@ -1,13 +1,16 @@
 pub mod audio;
+pub mod capture;
+pub mod crypto;
 pub mod handlers;
 pub mod state;
+pub mod vad;
 
 use axum::{
     routing::{get, post},
     Router,
 };
 use handlers::{
-    handle_audio, handle_audio_file, handle_devices, handle_events,
+    handle_audio, handle_audio_file, handle_audio_stream, handle_devices, handle_events,
     handle_recording_start, handle_recording_stop, handle_recording_status,
     handle_status, handle_transcripts,
 };
@@ -22,6 +25,7 @@ use tower_http::{
 pub fn create_router(state: Arc<ServerState>) -> Router {
     Router::new()
         .route("/audio", post(handle_audio))
+        .route("/audio-stream", post(handle_audio_stream))
         .route("/audio-file", get(handle_audio_file))
         .route("/status", get(handle_status))
         .route("/recording-status", get(handle_recording_status))
@@ -1,11 +1,59 @@
 use memo_stt::SttEngine;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::sync::Mutex;
 use tokio::sync::mpsc;
 use chrono::{DateTime, Utc};
 
+/// Number of recent per-frame RMS samples kept to estimate the noise floor.
+const RMS_HISTORY_LEN: usize = 100;
+
+/// How much trailing audio (ms) to carry over into the next segment so
+/// words spoken right at a segment boundary aren't lost.
+pub const SEGMENT_OVERLAP_MS: f32 = 300.0;
+
+/// Silence duration (ms) after which an open segment is closed.
+pub const SEGMENT_SILENCE_CLOSE_MS: f32 = 500.0;
+
+/// Minimum amount of new voiced audio (ms) between partial re-transcriptions
+/// of an open segment. Without this, every ~20ms frame would re-transcribe
+/// the whole accumulated segment, and since that takes far longer than
+/// 20ms, tasks queue up behind the single `Mutex<SttEngine>` faster than
+/// they drain.
+pub const PARTIAL_DEBOUNCE_MS: f32 = 250.0;
+
+/// Per-device state for the streaming `/audio-stream` ingest path: the
+/// in-progress segment buffer plus enough history to run the energy-based
+/// voice-activity detector across frame boundaries.
+#[derive(Default)]
+pub struct StreamSegment {
+    pub samples: Vec<i16>,
+    pub rms_history: VecDeque<f32>,
+    pub silence_ms: f32,
+    pub voiced: bool,
+    pub ms_since_partial: f32,
+    pub partial_inflight: bool,
+}
+
+impl StreamSegment {
+    pub fn push_rms(&mut self, rms: f32) {
+        self.rms_history.push_back(rms);
+        if self.rms_history.len() > RMS_HISTORY_LEN {
+            self.rms_history.pop_front();
+        }
+    }
+}
+
+/// How `/audio` request bodies are carried over the wire. Kept as an enum
+/// so the plaintext path stays zero-cost and new schemes (e.g. per-device
+/// keys) can be added without touching handler logic.
+#[derive(Debug, Clone)]
+pub enum AudioTransport {
+    Plain,
+    Encrypted { key: Vec<u8> },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceInfo {
     pub device_id: String,
@@ -13,11 +61,22 @@ pub struct DeviceInfo {
     pub ip_address: Option<String>,
 }
 
+/// A timed slice of a transcript, as produced by Whisper's segment-level
+/// decoding. Lets the UI highlight words as the archived WAV plays and jump
+/// to a phrase instead of only showing a flat wall of text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start_sec: f32,
+    pub end_sec: f32,
+    pub text: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transcript {
     pub device_id: String,
     pub timestamp: DateTime<Utc>,
     pub text: String,
+    pub segments: Vec<TranscriptSegment>,
     pub audio_file: Option<String>,
     pub audio_quality: Option<serde_json::Value>,
     pub server_analysis: Option<serde_json::Value>,
@@ -29,16 +88,24 @@ pub struct ServerState {
     pub transcripts: Arc<Mutex<Vec<Transcript>>>,
     pub recording_state: Arc<Mutex<HashMap<String, bool>>>,
     pub sse_senders: Arc<Mutex<Vec<mpsc::UnboundedSender<String>>>>,
+    pub stream_segments: Arc<Mutex<HashMap<String, StreamSegment>>>,
+    pub transport: AudioTransport,
 }
 
 impl ServerState {
     pub fn new(engine: SttEngine) -> Self {
+        Self::with_transport(engine, AudioTransport::Plain)
+    }
+
+    pub fn with_transport(engine: SttEngine, transport: AudioTransport) -> Self {
         Self {
             engine: Arc::new(Mutex::new(engine)),
             devices: Arc::new(Mutex::new(HashMap::new())),
             transcripts: Arc::new(Mutex::new(Vec::new())),
             recording_state: Arc::new(Mutex::new(HashMap::new())),
             sse_senders: Arc::new(Mutex::new(Vec::new())),
+            stream_segments: Arc::new(Mutex::new(HashMap::new())),
+            transport,
         }
     }
 
@@ -0,0 +1,77 @@
+//! Minimal energy-based voice-activity detection for the streaming ingest path.
+
+/// Root-mean-square energy of a frame of 16-bit PCM samples.
+pub fn frame_rms(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    ((sum_sq / samples.len() as f64).sqrt()) as f32
+}
+
+/// Estimate of the ambient noise floor from recent frame energies, as the
+/// 10th percentile RMS. Returns 0.0 for an empty history; with a single
+/// entry, that entry is the floor.
+pub fn noise_floor(history: &[f32]) -> f32 {
+    if history.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = history.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let idx = ((sorted.len() as f32) * 0.1) as usize;
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+/// A frame is voiced when its energy exceeds the noise floor by this factor.
+pub const VOICED_THRESHOLD_FACTOR: f32 = 3.0;
+
+/// Decide whether a frame is voiced given the current noise floor estimate.
+pub fn is_voiced(rms: f32, noise_floor: f32) -> bool {
+    rms > noise_floor * VOICED_THRESHOLD_FACTOR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rms_of_silence_is_zero() {
+        assert_eq!(frame_rms(&[0, 0, 0, 0]), 0.0);
+    }
+
+    #[test]
+    fn frame_rms_of_empty_frame_is_zero() {
+        assert_eq!(frame_rms(&[]), 0.0);
+    }
+
+    #[test]
+    fn frame_rms_matches_known_value() {
+        // RMS of a constant-amplitude signal is just the amplitude.
+        assert_eq!(frame_rms(&[100, -100, 100, -100]), 100.0);
+    }
+
+    #[test]
+    fn noise_floor_of_empty_history_is_zero() {
+        assert_eq!(noise_floor(&[]), 0.0);
+    }
+
+    #[test]
+    fn noise_floor_is_roughly_10th_percentile() {
+        let history: Vec<f32> = (1..=100).map(|n| n as f32).collect();
+        // 10th percentile of 1..=100 should sit near the low end of the range.
+        let floor = noise_floor(&history);
+        assert!(floor >= 1.0 && floor <= 15.0, "floor was {floor}");
+    }
+
+    #[test]
+    fn is_voiced_above_threshold() {
+        assert!(is_voiced(100.0, 10.0));
+    }
+
+    #[test]
+    fn is_voiced_below_threshold() {
+        assert!(!is_voiced(20.0, 10.0));
+    }
+}